@@ -3,13 +3,17 @@
 
 use std::net::SocketAddr;
 use std::str::FromStr;
+use std::time::Duration;
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use bitcoin_hashes::sha256::Hash;
-use nostr_sdk_base::{Contact, Event, Keys, SubscriptionFilter};
+use nostr_sdk_base::{Contact, Event, Keys, Metadata, SubscriptionFilter};
 use tokio::sync::broadcast;
 
-use crate::relay::{RelayPool, RelayPoolNotifications};
+use crate::relay::{
+    RelayConnectionEvent, RelayPool, RelayPoolNotifications, RelayServiceFlags,
+    SubscribeAutoCloseOptions,
+};
 #[cfg(feature = "blocking")]
 use crate::RUNTIME;
 
@@ -28,11 +32,50 @@ impl Client {
         }
     }
 
+    /// Like [`Client::new`], but replaying `notification_buffer_size` recent
+    /// notifications to a `notifications()` subscriber that attaches late (the
+    /// default is [`crate::relay::DEFAULT_NOTIFICATION_BUFFER_SIZE`]).
+    pub fn with_notification_buffer_size(
+        keys: &Keys,
+        contacts: Option<Vec<Contact>>,
+        notification_buffer_size: usize,
+    ) -> Self {
+        Self {
+            pool: RelayPool::with_notification_buffer_size(notification_buffer_size),
+            keys: keys.clone(),
+            contacts: contacts.unwrap_or_default(),
+        }
+    }
+
     pub fn generate_keys() -> Keys {
         Keys::generate_from_os_random()
     }
 }
 
+/// Turn a `send_event` per-relay outcome into a single `Result`, so a caller publishing
+/// a single event doesn't have to inspect the per-relay breakdown itself: it's an error
+/// unless at least one relay accepted it.
+fn ensure_published(outcomes: &[(String, Result<()>)]) -> Result<()> {
+    if outcomes.is_empty() {
+        return Err(anyhow!("no relay is configured to publish to"));
+    }
+
+    if outcomes.iter().any(|(_, result)| result.is_ok()) {
+        return Ok(());
+    }
+
+    let reasons = outcomes
+        .iter()
+        .map(|(url, result)| match result {
+            Ok(()) => unreachable!(),
+            Err(e) => format!("{url}: {e}"),
+        })
+        .collect::<Vec<_>>()
+        .join("; ");
+
+    Err(anyhow!("event was rejected by every relay ({reasons})"))
+}
+
 #[cfg(not(feature = "blocking"))]
 impl Client {
     pub async fn add_contact(&mut self, contact: Contact) {
@@ -51,10 +94,28 @@ impl Client {
         self.pool.notifications()
     }
 
+    /// A separate, bounded stream of relay connection/disconnection/reconnect events
+    /// and periodic per-relay stats, independent from `notifications()` so a consumer
+    /// that only cares about connection health isn't forced to drain Nostr events too.
+    pub async fn connection_events(&self) -> broadcast::Receiver<RelayConnectionEvent> {
+        self.pool.connection_events()
+    }
+
     pub async fn add_relay(&mut self, url: &str, proxy: Option<SocketAddr>) -> Result<()> {
         self.pool.add_relay(url, proxy)
     }
 
+    /// Add a relay marked with the given read/write [`RelayServiceFlags`], so
+    /// `subscribe` and `send_event` only use it for the services it's flagged for.
+    pub async fn add_relay_with_opts(
+        &mut self,
+        url: &str,
+        proxy: Option<SocketAddr>,
+        flags: RelayServiceFlags,
+    ) -> Result<()> {
+        self.pool.add_relay_with_opts(url, proxy, flags)
+    }
+
     pub async fn remove_relay(&mut self, url: &str) -> Result<()> {
         self.pool.remove_relay(url).await
     }
@@ -76,13 +137,50 @@ impl Client {
         self.pool.subscribe(filters).await
     }
 
-    pub async fn send_event(&self, event: Event) -> Result<()> {
+    /// Subscribe to `filters`, optionally tearing the subscription down automatically
+    /// once every relay has sent `EOSE` for it, so one-shot historical queries don't
+    /// need to track the subscription id and send `CLOSE` themselves.
+    pub async fn subscribe_with_opts(
+        &mut self,
+        filters: Vec<SubscriptionFilter>,
+        auto_close: Option<SubscribeAutoCloseOptions>,
+    ) -> Result<()> {
+        self.pool.subscribe_with_opts(filters, auto_close).await
+    }
+
+    /// Publish `event` to every WRITE relay concurrently, returning the per-relay
+    /// outcome so callers know which relays accepted it and which timed out.
+    pub async fn send_event(&self, event: Event) -> Result<Vec<(String, Result<()>)>> {
         self.pool.send_event(event).await
     }
 
+    /// Open a temporary subscription for `filters`, collect every stored event relays
+    /// send back for it, and close the subscription again once all relays report
+    /// `EOSE` (or `timeout` elapses, whichever comes first).
+    pub async fn get_events_of(
+        &self,
+        filters: Vec<SubscriptionFilter>,
+        timeout: Option<Duration>,
+    ) -> Result<Vec<Event>> {
+        self.pool.get_events_of(filters, timeout).await
+    }
+
     pub async fn delete_event(&self, event_id: &str) -> Result<()> {
         let event = Event::delete(&self.keys, vec![Hash::from_str(event_id)?], None)?;
-        self.send_event(event).await
+        ensure_published(&self.send_event(event).await?)
+    }
+
+    /// Publish `self.contacts` as a NIP-02 kind-3 contact list, replacing whatever
+    /// contact list this account previously published.
+    pub async fn publish_contact_list(&self) -> Result<()> {
+        let event = Event::set_contact_list(&self.keys, self.contacts.clone(), None)?;
+        ensure_published(&self.send_event(event).await?)
+    }
+
+    /// Publish a NIP-01 kind-0 event setting this account's profile metadata.
+    pub async fn set_metadata(&self, metadata: Metadata) -> Result<()> {
+        let event = Event::set_metadata(&self.keys, metadata, None)?;
+        ensure_published(&self.send_event(event).await?)
     }
 
     pub async fn handle_notifications<F>(&self, func: F) -> Result<()>
@@ -121,10 +219,28 @@ impl Client {
         RUNTIME.block_on(async { self.pool.notifications() })
     }
 
+    /// A separate, bounded stream of relay connection/disconnection/reconnect events
+    /// and periodic per-relay stats, independent from `notifications()` so a consumer
+    /// that only cares about connection health isn't forced to drain Nostr events too.
+    pub fn connection_events(&self) -> broadcast::Receiver<RelayConnectionEvent> {
+        RUNTIME.block_on(async { self.pool.connection_events() })
+    }
+
     pub fn add_relay(&mut self, url: &str, proxy: Option<SocketAddr>) -> Result<()> {
         RUNTIME.block_on(async { self.pool.add_relay(url, proxy) })
     }
 
+    /// Add a relay marked with the given read/write [`RelayServiceFlags`], so
+    /// `subscribe` and `send_event` only use it for the services it's flagged for.
+    pub fn add_relay_with_opts(
+        &mut self,
+        url: &str,
+        proxy: Option<SocketAddr>,
+        flags: RelayServiceFlags,
+    ) -> Result<()> {
+        RUNTIME.block_on(async { self.pool.add_relay_with_opts(url, proxy, flags) })
+    }
+
     pub fn remove_relay(&mut self, url: &str) -> Result<()> {
         RUNTIME.block_on(async { self.pool.remove_relay(url).await })
     }
@@ -146,13 +262,50 @@ impl Client {
         RUNTIME.block_on(async { self.pool.subscribe(filters).await })
     }
 
-    pub fn send_event(&self, event: Event) -> Result<()> {
+    /// Subscribe to `filters`, optionally tearing the subscription down automatically
+    /// once every relay has sent `EOSE` for it, so one-shot historical queries don't
+    /// need to track the subscription id and send `CLOSE` themselves.
+    pub fn subscribe_with_opts(
+        &mut self,
+        filters: Vec<SubscriptionFilter>,
+        auto_close: Option<SubscribeAutoCloseOptions>,
+    ) -> Result<()> {
+        RUNTIME.block_on(async { self.pool.subscribe_with_opts(filters, auto_close).await })
+    }
+
+    /// Publish `event` to every WRITE relay concurrently, returning the per-relay
+    /// outcome so callers know which relays accepted it and which timed out.
+    pub fn send_event(&self, event: Event) -> Result<Vec<(String, Result<()>)>> {
         RUNTIME.block_on(async { self.pool.send_event(event).await })
     }
 
+    /// Open a temporary subscription for `filters`, collect every stored event relays
+    /// send back for it, and close the subscription again once all relays report
+    /// `EOSE` (or `timeout` elapses, whichever comes first).
+    pub fn get_events_of(
+        &self,
+        filters: Vec<SubscriptionFilter>,
+        timeout: Option<Duration>,
+    ) -> Result<Vec<Event>> {
+        RUNTIME.block_on(async { self.pool.get_events_of(filters, timeout).await })
+    }
+
     pub fn delete_event(&self, event_id: &str) -> Result<()> {
         let event = Event::delete(&self.keys, vec![Hash::from_str(event_id)?], None)?;
-        self.send_event(event)
+        ensure_published(&self.send_event(event)?)
+    }
+
+    /// Publish `self.contacts` as a NIP-02 kind-3 contact list, replacing whatever
+    /// contact list this account previously published.
+    pub fn publish_contact_list(&self) -> Result<()> {
+        let event = Event::set_contact_list(&self.keys, self.contacts.clone(), None)?;
+        ensure_published(&self.send_event(event)?)
+    }
+
+    /// Publish a NIP-01 kind-0 event setting this account's profile metadata.
+    pub fn set_metadata(&self, metadata: Metadata) -> Result<()> {
+        let event = Event::set_metadata(&self.keys, metadata, None)?;
+        ensure_published(&self.send_event(event)?)
     }
 
     pub fn handle_notifications<F>(&self, func: F) -> Result<()>
@@ -169,4 +322,32 @@ impl Client {
             }
         })
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ensure_published_errors_with_no_relays() {
+        assert!(ensure_published(&[]).is_err());
+    }
+
+    #[test]
+    fn ensure_published_errors_when_every_relay_rejects() {
+        let outcomes = vec![
+            ("wss://a.test".to_string(), Err(anyhow!("rejected"))),
+            ("wss://b.test".to_string(), Err(anyhow!("timed out"))),
+        ];
+        assert!(ensure_published(&outcomes).is_err());
+    }
+
+    #[test]
+    fn ensure_published_succeeds_when_at_least_one_relay_accepts() {
+        let outcomes = vec![
+            ("wss://a.test".to_string(), Err(anyhow!("rejected"))),
+            ("wss://b.test".to_string(), Ok(())),
+        ];
+        assert!(ensure_published(&outcomes).is_ok());
+    }
 }
\ No newline at end of file
@@ -0,0 +1,24 @@
+// Copyright (c) 2022 Yuki Kishimoto
+// Distributed under the MIT software license
+
+pub extern crate nostr_sdk_base as base;
+
+mod client;
+mod relay;
+
+pub use nostr_sdk_base::*;
+
+pub use crate::client::Client;
+pub use crate::relay::{
+    Relay, RelayConnectionEvent, RelayPool, RelayPoolNotifications, RelayServiceFlags,
+    RelayStatus, SubscribeAutoCloseOptions, DEFAULT_NOTIFICATION_BUFFER_SIZE,
+};
+
+#[cfg(feature = "blocking")]
+use once_cell::sync::Lazy;
+#[cfg(feature = "blocking")]
+use tokio::runtime::Runtime;
+
+#[cfg(feature = "blocking")]
+static RUNTIME: Lazy<Runtime> =
+    Lazy::new(|| Runtime::new().expect("Could not create Tokio runtime"));
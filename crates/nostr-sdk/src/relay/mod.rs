@@ -0,0 +1,319 @@
+// Copyright (c) 2022 Yuki Kishimoto
+// Distributed under the MIT software license
+
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex as StdMutex};
+use std::time::Duration;
+
+use anyhow::Result;
+use futures_util::{SinkExt, StreamExt};
+use nostr_sdk_base::{ClientMessage, RelayMessage};
+use tokio::net::TcpStream;
+use tokio::sync::{mpsc, Mutex};
+use tokio_tungstenite::tungstenite::Message as WsMessage;
+use tokio_tungstenite::{connect_async, MaybeTlsStream, WebSocketStream};
+use url::Url;
+
+pub mod pool;
+
+pub use self::pool::{
+    RelayPool, RelayPoolNotifications, SubscribeAutoCloseOptions,
+    DEFAULT_NOTIFICATION_BUFFER_SIZE,
+};
+
+/// Size of the bounded channel used to queue outgoing messages for a single relay.
+const RELAY_SEND_QUEUE_SIZE: usize = 256;
+/// How often a connected relay reports message counters on the monitoring stream.
+const RELAY_STATS_INTERVAL: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RelayStatus {
+    Initialized,
+    Connecting,
+    Connected,
+    Disconnected,
+    Stopped,
+}
+
+/// Events produced by a single relay's connection task and forwarded to the pool.
+#[derive(Debug, Clone)]
+pub(crate) enum RelayEvent {
+    Message(RelayMessage),
+    Disconnected,
+}
+
+/// Transport-level events for a relay, independent of the Nostr events it carries.
+///
+/// Surfaced on `Client::connection_events()` so a UI can render connection health or
+/// drive reconnect logic without having to drain `RelayPoolNotifications` as well.
+#[derive(Debug, Clone)]
+pub enum RelayConnectionEvent {
+    Connected {
+        url: String,
+    },
+    Disconnected {
+        url: String,
+        reason: Option<String>,
+    },
+    Reconnecting {
+        url: String,
+        attempt: u64,
+    },
+    Stats {
+        url: String,
+        messages_sent: u64,
+        messages_received: u64,
+    },
+}
+
+bitflags::bitflags! {
+    /// Marks whether a relay should be used for reading (subscriptions), writing
+    /// (publishing events), or both. Lets callers read from large public relays while
+    /// only publishing to their own.
+    #[derive(Default)]
+    pub struct RelayServiceFlags: u8 {
+        const READ = 1 << 0;
+        const WRITE = 1 << 1;
+    }
+}
+
+impl RelayServiceFlags {
+    pub fn read_write() -> Self {
+        Self::READ | Self::WRITE
+    }
+}
+
+pub struct Relay {
+    url: Url,
+    proxy: Option<SocketAddr>,
+    flags: RelayServiceFlags,
+    status: Arc<Mutex<RelayStatus>>,
+    scheduled_for_stop: Arc<AtomicBool>,
+    reconnect_attempts: Arc<AtomicU64>,
+    relay_sender: StdMutex<mpsc::Sender<ClientMessage>>,
+}
+
+impl Relay {
+    /// Create a new relay and spawn its connection task.
+    ///
+    /// `pool_sender` is how the relay reports incoming messages (and disconnects) back
+    /// to the owning `RelayPool`; `monitor_sender` carries transport-level
+    /// [`RelayConnectionEvent`]s on a separate, independent stream.
+    pub fn new(
+        url: &str,
+        proxy: Option<SocketAddr>,
+        flags: RelayServiceFlags,
+        pool_sender: mpsc::Sender<(String, RelayEvent)>,
+        monitor_sender: mpsc::Sender<RelayConnectionEvent>,
+    ) -> Result<Self> {
+        let url: Url = Url::parse(url)?;
+        let (relay_sender, relay_receiver) = mpsc::channel::<ClientMessage>(RELAY_SEND_QUEUE_SIZE);
+
+        let relay = Self {
+            url,
+            proxy,
+            flags,
+            status: Arc::new(Mutex::new(RelayStatus::Initialized)),
+            scheduled_for_stop: Arc::new(AtomicBool::new(false)),
+            reconnect_attempts: Arc::new(AtomicU64::new(0)),
+            relay_sender: StdMutex::new(relay_sender),
+        };
+        relay.spawn_connection(relay_receiver, pool_sender, monitor_sender);
+
+        Ok(relay)
+    }
+
+    fn spawn_connection(
+        &self,
+        relay_receiver: mpsc::Receiver<ClientMessage>,
+        pool_sender: mpsc::Sender<(String, RelayEvent)>,
+        monitor_sender: mpsc::Sender<RelayConnectionEvent>,
+    ) {
+        let url = self.url.clone();
+        let status = self.status.clone();
+        let scheduled_for_stop = self.scheduled_for_stop.clone();
+        scheduled_for_stop.store(false, Ordering::SeqCst);
+
+        tokio::spawn(async move {
+            Self::connection_loop(
+                url,
+                status,
+                scheduled_for_stop,
+                relay_receiver,
+                pool_sender,
+                monitor_sender,
+            )
+            .await;
+        });
+    }
+
+    /// Reconnect a relay that was previously disconnected or stopped.
+    pub fn connect(
+        &self,
+        pool_sender: mpsc::Sender<(String, RelayEvent)>,
+        monitor_sender: mpsc::Sender<RelayConnectionEvent>,
+    ) -> Result<()> {
+        let attempt = self.reconnect_attempts.fetch_add(1, Ordering::SeqCst) + 1;
+        let _ = monitor_sender.try_send(RelayConnectionEvent::Reconnecting {
+            url: self.url(),
+            attempt,
+        });
+
+        let (relay_sender, relay_receiver) = mpsc::channel::<ClientMessage>(RELAY_SEND_QUEUE_SIZE);
+        *self
+            .relay_sender
+            .lock()
+            .unwrap_or_else(|e| e.into_inner()) = relay_sender;
+        self.spawn_connection(relay_receiver, pool_sender, monitor_sender);
+        Ok(())
+    }
+
+    pub fn url(&self) -> String {
+        self.url.to_string()
+    }
+
+    pub fn flags(&self) -> RelayServiceFlags {
+        self.flags
+    }
+
+    pub async fn status(&self) -> RelayStatus {
+        *self.status.lock().await
+    }
+
+    /// Sender used to queue outgoing messages for this relay.
+    ///
+    /// Bounded so a single stalled relay can never block the pool: the pool either uses
+    /// `try_send` or waits at most `RELAY_SEND_TIMEOUT`, and treats a full queue as a
+    /// per-relay failure.
+    pub fn sender(&self) -> mpsc::Sender<ClientMessage> {
+        self.relay_sender
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    pub fn stop(&self) {
+        self.scheduled_for_stop.store(true, Ordering::SeqCst);
+    }
+
+    /// Build a relay without connecting to a real websocket, for `RelayPool` tests.
+    ///
+    /// Returns the relay alongside the receiving half of its outgoing queue, so a test
+    /// can assert on what the pool tried to send it while driving `pool_sender`/
+    /// `monitor_sender` by hand to simulate incoming messages.
+    #[cfg(test)]
+    pub(crate) fn new_for_test(
+        url: &str,
+        flags: RelayServiceFlags,
+    ) -> (Self, mpsc::Receiver<ClientMessage>) {
+        let url: Url = Url::parse(url).expect("valid relay url");
+        let (relay_sender, relay_receiver) = mpsc::channel::<ClientMessage>(RELAY_SEND_QUEUE_SIZE);
+
+        let relay = Self {
+            url,
+            proxy: None,
+            flags,
+            status: Arc::new(Mutex::new(RelayStatus::Connected)),
+            scheduled_for_stop: Arc::new(AtomicBool::new(false)),
+            reconnect_attempts: Arc::new(AtomicU64::new(0)),
+            relay_sender: StdMutex::new(relay_sender),
+        };
+
+        (relay, relay_receiver)
+    }
+
+    async fn connection_loop(
+        url: Url,
+        status: Arc<Mutex<RelayStatus>>,
+        scheduled_for_stop: Arc<AtomicBool>,
+        mut relay_receiver: mpsc::Receiver<ClientMessage>,
+        pool_sender: mpsc::Sender<(String, RelayEvent)>,
+        monitor_sender: mpsc::Sender<RelayConnectionEvent>,
+    ) {
+        *status.lock().await = RelayStatus::Connecting;
+
+        let (ws_stream, _): (WebSocketStream<MaybeTlsStream<TcpStream>>, _) =
+            match connect_async(url.as_str()).await {
+                Ok(stream) => stream,
+                Err(e) => {
+                    *status.lock().await = RelayStatus::Disconnected;
+                    let _ = pool_sender
+                        .send((url.to_string(), RelayEvent::Disconnected))
+                        .await;
+                    let _ = monitor_sender
+                        .send(RelayConnectionEvent::Disconnected {
+                            url: url.to_string(),
+                            reason: Some(e.to_string()),
+                        })
+                        .await;
+                    return;
+                }
+            };
+
+        *status.lock().await = RelayStatus::Connected;
+        let _ = monitor_sender
+            .send(RelayConnectionEvent::Connected {
+                url: url.to_string(),
+            })
+            .await;
+
+        let (mut ws_tx, mut ws_rx) = ws_stream.split();
+        let mut messages_sent: u64 = 0;
+        let mut messages_received: u64 = 0;
+        let mut stats_interval = tokio::time::interval(RELAY_STATS_INTERVAL);
+        let mut disconnect_reason: Option<String> = None;
+
+        loop {
+            if scheduled_for_stop.load(Ordering::SeqCst) {
+                break;
+            }
+
+            tokio::select! {
+                Some(msg) = relay_receiver.recv() => {
+                    if ws_tx.send(WsMessage::Text(msg.as_json())).await.is_err() {
+                        disconnect_reason = Some("failed to write to the relay".to_string());
+                        break;
+                    }
+                    messages_sent += 1;
+                }
+                Some(msg) = ws_rx.next() => {
+                    match msg {
+                        Ok(WsMessage::Text(text)) => {
+                            messages_received += 1;
+                            if let Ok(relay_msg) = RelayMessage::from_json(&text) {
+                                let _ = pool_sender
+                                    .send((url.to_string(), RelayEvent::Message(relay_msg)))
+                                    .await;
+                            }
+                        }
+                        Ok(_) => {}
+                        Err(e) => {
+                            disconnect_reason = Some(e.to_string());
+                            break;
+                        }
+                    }
+                }
+                _ = stats_interval.tick() => {
+                    let _ = monitor_sender.try_send(RelayConnectionEvent::Stats {
+                        url: url.to_string(),
+                        messages_sent,
+                        messages_received,
+                    });
+                }
+                else => break,
+            }
+        }
+
+        *status.lock().await = RelayStatus::Disconnected;
+        let _ = pool_sender
+            .send((url.to_string(), RelayEvent::Disconnected))
+            .await;
+        let _ = monitor_sender
+            .send(RelayConnectionEvent::Disconnected {
+                url: url.to_string(),
+                reason: disconnect_reason,
+            })
+            .await;
+    }
+}
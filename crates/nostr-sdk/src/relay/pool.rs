@@ -0,0 +1,746 @@
+// Copyright (c) 2022 Yuki Kishimoto
+// Distributed under the MIT software license
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex as StdMutex};
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use futures_util::stream::FuturesUnordered;
+use futures_util::StreamExt;
+use nostr_sdk_base::{ClientMessage, Event, RelayMessage, SubscriptionFilter};
+use tokio::sync::{broadcast, mpsc};
+use uuid::Uuid;
+
+use super::{Relay, RelayConnectionEvent, RelayEvent, RelayServiceFlags, RelayStatus};
+
+const NOTIFICATION_CHANNEL_SIZE: usize = 1024;
+const POOL_TASK_CHANNEL_SIZE: usize = 1024;
+/// Bounded independently of `NOTIFICATION_CHANNEL_SIZE` so a consumer that only cares
+/// about connection health isn't forced to also drain incoming Nostr events.
+const CONNECTION_EVENT_CHANNEL_SIZE: usize = 256;
+/// How long to wait for a single relay to accept a message before treating it as a
+/// per-relay failure, instead of letting it block every other relay's send.
+const RELAY_SEND_TIMEOUT: Duration = Duration::from_secs(5);
+/// Default number of recent notifications replayed to a newly attached subscriber.
+pub const DEFAULT_NOTIFICATION_BUFFER_SIZE: usize = 64;
+
+#[derive(Debug, Clone)]
+pub enum RelayPoolNotifications {
+    /// An event received for the given subscription id.
+    ReceivedEvent(String, Event),
+    ReceivedMessage(RelayMessage),
+    /// All relays a subscription was opened on have sent `EOSE` for it.
+    Eose(String),
+}
+
+/// Bookkeeping the pool keeps for an open subscription so it can tell when every relay
+/// has caught up (`EOSE`).
+struct SubscriptionState {
+    relays: HashSet<String>,
+    eose_relays: HashSet<String>,
+}
+
+/// Options for a subscription that should tear itself down once relays catch up,
+/// instead of staying open for live events forever.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SubscribeAutoCloseOptions {
+    /// Keep the subscription open this much longer after the last event received
+    /// following `EOSE`, instead of closing the moment every relay reports `EOSE`.
+    pub idle_timeout: Option<Duration>,
+}
+
+#[derive(Clone)]
+pub struct RelayPool {
+    relays: Arc<StdMutex<HashMap<String, Relay>>>,
+    subscriptions: Arc<StdMutex<HashMap<String, SubscriptionState>>>,
+    notification_sender: broadcast::Sender<RelayPoolNotifications>,
+    /// Ring buffer of the most recent notifications, replayed to late subscribers.
+    backlog: Arc<StdMutex<VecDeque<RelayPoolNotifications>>>,
+    backlog_size: usize,
+    pool_sender: mpsc::Sender<(String, RelayEvent)>,
+    pool_receiver: Arc<StdMutex<Option<mpsc::Receiver<(String, RelayEvent)>>>>,
+    connection_sender: mpsc::Sender<RelayConnectionEvent>,
+    connection_notification_sender: broadcast::Sender<RelayConnectionEvent>,
+    connection_receiver: Arc<StdMutex<Option<mpsc::Receiver<RelayConnectionEvent>>>>,
+    task_started: Arc<AtomicBool>,
+}
+
+impl RelayPool {
+    pub fn new() -> Self {
+        Self::with_notification_buffer_size(DEFAULT_NOTIFICATION_BUFFER_SIZE)
+    }
+
+    /// Create a pool that replays the last `backlog_size` notifications to a
+    /// subscriber that attaches via `notifications()` after they were published.
+    pub fn with_notification_buffer_size(backlog_size: usize) -> Self {
+        let (notification_sender, _) = broadcast::channel(NOTIFICATION_CHANNEL_SIZE);
+        let (pool_sender, pool_receiver) = mpsc::channel(POOL_TASK_CHANNEL_SIZE);
+        let (connection_sender, connection_receiver) =
+            mpsc::channel(CONNECTION_EVENT_CHANNEL_SIZE);
+        let (connection_notification_sender, _) =
+            broadcast::channel(CONNECTION_EVENT_CHANNEL_SIZE);
+
+        Self {
+            relays: Arc::new(StdMutex::new(HashMap::new())),
+            subscriptions: Arc::new(StdMutex::new(HashMap::new())),
+            notification_sender,
+            backlog: Arc::new(StdMutex::new(VecDeque::with_capacity(backlog_size))),
+            backlog_size,
+            pool_sender,
+            pool_receiver: Arc::new(StdMutex::new(Some(pool_receiver))),
+            connection_sender,
+            connection_notification_sender,
+            connection_receiver: Arc::new(StdMutex::new(Some(connection_receiver))),
+            task_started: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// A separate, bounded stream of transport-level [`RelayConnectionEvent`]s, kept
+    /// independent from `notifications()` so a consumer that only cares about
+    /// connection health isn't forced to drain incoming Nostr events too.
+    pub fn connection_events(&self) -> broadcast::Receiver<RelayConnectionEvent> {
+        self.ensure_task_started();
+        self.connection_notification_sender.subscribe()
+    }
+
+    /// Subscribe to pool notifications. The backlog of recently published
+    /// notifications is replayed to the returned receiver before live delivery begins,
+    /// so a consumer that attaches late still sees recent activity.
+    pub fn notifications(&self) -> broadcast::Receiver<RelayPoolNotifications> {
+        let backlog: Vec<RelayPoolNotifications> = self
+            .backlog
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .cloned()
+            .collect();
+        let mut upstream = self.subscribe_live();
+        let (replay_sender, replay_receiver) =
+            broadcast::channel(backlog.len().max(1) + NOTIFICATION_CHANNEL_SIZE);
+
+        for notification in backlog {
+            let _ = replay_sender.send(notification);
+        }
+
+        tokio::spawn(async move {
+            loop {
+                match upstream.recv().await {
+                    Ok(notification) => {
+                        if replay_sender.send(notification).is_err() {
+                            break;
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        });
+
+        replay_receiver
+    }
+
+    /// Subscribe to live pool notifications only, skipping the replay backlog.
+    ///
+    /// Internal callers that wait on a specific subscription id (`get_events_of`,
+    /// `auto_close_subscription`) use this instead of `notifications()`: replaying past
+    /// activity to them is worse than useless, since a stale `Eose` or `ReceivedEvent`
+    /// left over from earlier traffic could be mistaken for the subscription they just
+    /// opened.
+    fn subscribe_live(&self) -> broadcast::Receiver<RelayPoolNotifications> {
+        self.ensure_task_started();
+        self.notification_sender.subscribe()
+    }
+
+    /// Push `notification` into the backlog (if enabled) and out to live subscribers.
+    fn publish_notification(&self, notification: RelayPoolNotifications) {
+        if self.backlog_size > 0 {
+            let mut backlog = self.backlog.lock().unwrap_or_else(|e| e.into_inner());
+            if backlog.len() == self.backlog_size {
+                backlog.pop_front();
+            }
+            backlog.push_back(notification.clone());
+        }
+        let _ = self.notification_sender.send(notification);
+    }
+
+    /// Spawn the task that fans incoming relay messages out to `notifications()`
+    /// subscribers. Runs once, lazily, on first use so constructing a pool doesn't
+    /// require an already-running Tokio runtime.
+    fn ensure_task_started(&self) {
+        if self
+            .task_started
+            .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
+            .is_err()
+        {
+            return;
+        }
+
+        let connection_receiver = self
+            .connection_receiver
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .take();
+        let connection_notification_sender = self.connection_notification_sender.clone();
+        tokio::spawn(async move {
+            let Some(mut connection_receiver) = connection_receiver else {
+                return;
+            };
+
+            while let Some(event) = connection_receiver.recv().await {
+                let _ = connection_notification_sender.send(event);
+            }
+        });
+
+        let pool_receiver = self
+            .pool_receiver
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .take();
+        let pool = self.clone();
+
+        tokio::spawn(async move {
+            let Some(mut pool_receiver) = pool_receiver else {
+                return;
+            };
+
+            while let Some((relay_url, event)) = pool_receiver.recv().await {
+                let RelayEvent::Message(msg) = event else {
+                    continue;
+                };
+
+                if let RelayMessage::EndOfStoredEvents(subscription_id) = &msg {
+                    let mut subscriptions =
+                        pool.subscriptions.lock().unwrap_or_else(|e| e.into_inner());
+                    if let Some(state) = subscriptions.get_mut(subscription_id) {
+                        state.eose_relays.insert(relay_url.clone());
+                        if state.eose_relays.is_superset(&state.relays) {
+                            pool.publish_notification(RelayPoolNotifications::Eose(
+                                subscription_id.clone(),
+                            ));
+                        }
+                    }
+                }
+
+                if let RelayMessage::Event {
+                    subscription_id,
+                    event,
+                } = &msg
+                {
+                    pool.publish_notification(RelayPoolNotifications::ReceivedEvent(
+                        subscription_id.clone(),
+                        event.clone(),
+                    ));
+                }
+
+                pool.publish_notification(RelayPoolNotifications::ReceivedMessage(msg));
+            }
+        });
+    }
+
+    pub fn add_relay(&self, url: &str, proxy: Option<SocketAddr>) -> Result<()> {
+        self.add_relay_with_opts(url, proxy, RelayServiceFlags::read_write())
+    }
+
+    pub fn add_relay_with_opts(
+        &self,
+        url: &str,
+        proxy: Option<SocketAddr>,
+        flags: RelayServiceFlags,
+    ) -> Result<()> {
+        self.ensure_task_started();
+        let relay = Relay::new(
+            url,
+            proxy,
+            flags,
+            self.pool_sender.clone(),
+            self.connection_sender.clone(),
+        )?;
+        self.relays
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(relay.url(), relay);
+        Ok(())
+    }
+
+    pub async fn remove_relay(&self, url: &str) -> Result<()> {
+        let relay = self
+            .relays
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(url);
+        match relay {
+            Some(relay) => {
+                relay.stop();
+                Ok(())
+            }
+            None => Err(anyhow!("relay {} is not in the pool", url)),
+        }
+    }
+
+    pub async fn connect_relay(&self, url: &str) -> Result<()> {
+        let relays = self.relays.lock().unwrap_or_else(|e| e.into_inner());
+        let relay = relays
+            .get(url)
+            .ok_or_else(|| anyhow!("relay {} is not in the pool", url))?;
+        relay.connect(self.pool_sender.clone(), self.connection_sender.clone())
+    }
+
+    pub async fn disconnect_relay(&self, url: &str) -> Result<()> {
+        let relays = self.relays.lock().unwrap_or_else(|e| e.into_inner());
+        let relay = relays
+            .get(url)
+            .ok_or_else(|| anyhow!("relay {} is not in the pool", url))?;
+        relay.stop();
+        Ok(())
+    }
+
+    /// Connect to all disconnected relays
+    pub async fn connect_all(&self) -> Result<()> {
+        let relays: Vec<(String, Arc<tokio::sync::Mutex<RelayStatus>>)> = self
+            .relays
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .map(|(url, relay)| (url.clone(), relay.status.clone()))
+            .collect();
+
+        for (url, status) in relays {
+            let status = *status.lock().await;
+            if matches!(status, RelayStatus::Disconnected | RelayStatus::Stopped) {
+                self.connect_relay(&url).await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub async fn subscribe(&self, filters: Vec<SubscriptionFilter>) -> Result<()> {
+        self.subscribe_with_id(&Uuid::new_v4().to_string(), filters)
+            .await?;
+        Ok(())
+    }
+
+    /// Subscribe to `filters`, optionally tearing the subscription down automatically
+    /// once every relay has sent `EOSE` for it (see [`SubscribeAutoCloseOptions`]).
+    pub async fn subscribe_with_opts(
+        &self,
+        filters: Vec<SubscriptionFilter>,
+        auto_close: Option<SubscribeAutoCloseOptions>,
+    ) -> Result<()> {
+        let subscription_id = Uuid::new_v4().to_string();
+        // Listen before the REQ goes out, so an `EOSE` that arrives before the
+        // auto-close task first runs is still seen.
+        let notifications = self.subscribe_live();
+        let has_relays = self.subscribe_with_id(&subscription_id, filters).await?;
+
+        if let Some(opts) = auto_close {
+            if has_relays {
+                let pool = self.clone();
+                tokio::spawn(async move {
+                    pool.auto_close_subscription(subscription_id, opts, notifications)
+                        .await;
+                });
+            } else {
+                // No relay was subscribed, so `EOSE` will never arrive for it: there's
+                // nothing to wait on, so tear down straight away instead of hanging.
+                self.close_subscription(&subscription_id).await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Wait for every relay to report `EOSE` for `subscription_id`, then (optionally,
+    /// after `idle_timeout` passes with no further events) send `CLOSE`.
+    async fn auto_close_subscription(
+        &self,
+        subscription_id: String,
+        opts: SubscribeAutoCloseOptions,
+        mut notifications: broadcast::Receiver<RelayPoolNotifications>,
+    ) {
+        loop {
+            match notifications.recv().await {
+                Ok(RelayPoolNotifications::Eose(id)) if id == subscription_id => break,
+                Ok(_) => continue,
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => return,
+            }
+        }
+
+        if let Some(idle_timeout) = opts.idle_timeout {
+            // Only events belonging to *this* subscription should push the deadline
+            // out; unrelated pool traffic (e.g. another, long-running subscribe()) must
+            // not keep resetting it, or idle_timeout would never actually fire.
+            let mut deadline = tokio::time::Instant::now() + idle_timeout;
+            loop {
+                let remaining = deadline.saturating_duration_since(tokio::time::Instant::now());
+                if remaining.is_zero() {
+                    break;
+                }
+
+                match tokio::time::timeout(remaining, notifications.recv()).await {
+                    Ok(Ok(RelayPoolNotifications::ReceivedEvent(id, _))) if id == subscription_id => {
+                        deadline = tokio::time::Instant::now() + idle_timeout;
+                    }
+                    Ok(Ok(_)) => continue,
+                    Ok(Err(broadcast::error::RecvError::Lagged(_))) => continue,
+                    Ok(Err(broadcast::error::RecvError::Closed)) => break,
+                    Err(_) => break,
+                }
+            }
+        }
+
+        let _ = self.close_subscription(&subscription_id).await;
+    }
+
+    /// Subscribe on every READ relay, returning whether there was any relay to
+    /// subscribe on. When `false`, no `SubscriptionState` was tracked and no relay will
+    /// ever report `EOSE` for this id, so callers waiting on `EOSE` should treat the
+    /// subscription as already caught up instead of waiting forever.
+    async fn subscribe_with_id(
+        &self,
+        subscription_id: &str,
+        filters: Vec<SubscriptionFilter>,
+    ) -> Result<bool> {
+        let relays: Vec<(String, mpsc::Sender<ClientMessage>)> = {
+            let relays = self.relays.lock().unwrap_or_else(|e| e.into_inner());
+            relays
+                .values()
+                .filter(|relay| relay.flags().contains(RelayServiceFlags::READ))
+                .map(|relay| (relay.url(), relay.sender()))
+                .collect()
+        };
+
+        if relays.is_empty() {
+            return Ok(false);
+        }
+
+        self.subscriptions.lock().unwrap_or_else(|e| e.into_inner()).insert(
+            subscription_id.to_string(),
+            SubscriptionState {
+                relays: relays.iter().map(|(url, _)| url.clone()).collect(),
+                eose_relays: HashSet::new(),
+            },
+        );
+
+        let msg = ClientMessage::new_req(subscription_id, filters);
+        let mut sends = FuturesUnordered::new();
+        for (url, sender) in relays {
+            let msg = msg.clone();
+            sends.push(async move {
+                let result = match tokio::time::timeout(RELAY_SEND_TIMEOUT, sender.send(msg)).await
+                {
+                    Ok(Ok(())) => Ok(()),
+                    Ok(Err(_)) => Err(anyhow!("relay {} send queue is closed", url)),
+                    Err(_) => Err(anyhow!("relay {} timed out accepting the subscription", url)),
+                };
+                (url, result)
+            });
+        }
+
+        // Drain every send (like `send_event` does) instead of bailing on the first
+        // error: a relay that already succeeded before another one failed still has a
+        // live REQ open and needs a `CLOSE`, which we can't target correctly if we've
+        // stopped tracking the subscription or abandoned the in-flight sends.
+        let mut outcomes = Vec::with_capacity(sends.len());
+        while let Some(outcome) = sends.next().await {
+            outcomes.push(outcome);
+        }
+
+        if outcomes.iter().any(|(_, result)| result.is_err()) {
+            // Some relay never got the REQ: tear the whole subscription down, which
+            // also sends `CLOSE` to the relays that did succeed, rather than leaving a
+            // server-side subscription the pool no longer tracks.
+            self.close_subscription(subscription_id).await?;
+
+            let reasons = outcomes
+                .iter()
+                .filter_map(|(url, result)| result.as_ref().err().map(|e| format!("{url}: {e}")))
+                .collect::<Vec<_>>()
+                .join("; ");
+            return Err(anyhow!("failed to subscribe on every relay ({reasons})"));
+        }
+
+        Ok(true)
+    }
+
+    async fn close_subscription(&self, subscription_id: &str) -> Result<()> {
+        self.subscriptions
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(subscription_id);
+
+        let senders: Vec<mpsc::Sender<ClientMessage>> = self
+            .relays
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .values()
+            .map(|relay| relay.sender())
+            .collect();
+
+        let msg = ClientMessage::new_close(subscription_id);
+        for sender in senders {
+            let _ = sender.try_send(msg.clone());
+        }
+
+        Ok(())
+    }
+
+    /// Publish `event` to every WRITE relay concurrently and report what happened on
+    /// each one individually, so a single blocked or stalled relay can't hold up (or
+    /// hide the failure of) the others.
+    pub async fn send_event(&self, event: Event) -> Result<Vec<(String, Result<()>)>> {
+        let relays: Vec<(String, mpsc::Sender<ClientMessage>)> = {
+            let relays = self.relays.lock().unwrap_or_else(|e| e.into_inner());
+            relays
+                .values()
+                .filter(|relay| relay.flags().contains(RelayServiceFlags::WRITE))
+                .map(|relay| (relay.url(), relay.sender()))
+                .collect()
+        };
+
+        let msg = ClientMessage::new_event(event);
+        let mut sends = FuturesUnordered::new();
+        for (url, sender) in relays {
+            let msg = msg.clone();
+            sends.push(async move {
+                let result = match tokio::time::timeout(RELAY_SEND_TIMEOUT, sender.send(msg)).await
+                {
+                    Ok(Ok(())) => Ok(()),
+                    Ok(Err(_)) => Err(anyhow!("relay {} send queue is closed", url)),
+                    Err(_) => Err(anyhow!("relay {} timed out accepting the event", url)),
+                };
+                (url, result)
+            });
+        }
+
+        let mut outcomes = Vec::with_capacity(sends.len());
+        while let Some(outcome) = sends.next().await {
+            outcomes.push(outcome);
+        }
+
+        Ok(outcomes)
+    }
+
+    /// Open a temporary subscription, collect every event relays have stored for it,
+    /// and close the subscription again once all relays report `EOSE` (or `timeout`
+    /// elapses, whichever comes first).
+    pub async fn get_events_of(
+        &self,
+        filters: Vec<SubscriptionFilter>,
+        timeout: Option<std::time::Duration>,
+    ) -> Result<Vec<Event>> {
+        let subscription_id = Uuid::new_v4().to_string();
+        let mut notifications = self.subscribe_live();
+
+        let has_relays = self.subscribe_with_id(&subscription_id, filters).await?;
+
+        let mut events: Vec<Event> = Vec::new();
+
+        if has_relays {
+            let collect = async {
+                loop {
+                    match notifications.recv().await {
+                        Ok(RelayPoolNotifications::ReceivedEvent(id, event))
+                            if id == subscription_id =>
+                        {
+                            events.push(event)
+                        }
+                        Ok(RelayPoolNotifications::Eose(id)) if id == subscription_id => break,
+                        Ok(_) => {}
+                        Err(broadcast::error::RecvError::Closed) => break,
+                        Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    }
+                }
+            };
+
+            match timeout {
+                Some(timeout) => {
+                    let _ = tokio::time::timeout(timeout, collect).await;
+                }
+                None => collect.await,
+            }
+        }
+
+        self.close_subscription(&subscription_id).await?;
+
+        Ok(events)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn subscribe_live_skips_the_replay_backlog() {
+        let pool = RelayPool::new();
+        pool.publish_notification(RelayPoolNotifications::Eose("stale".to_string()));
+
+        // A late `notifications()` subscriber still sees it...
+        let mut replayed = pool.notifications();
+        assert!(matches!(
+            replayed.try_recv(),
+            Ok(RelayPoolNotifications::Eose(id)) if id == "stale"
+        ));
+
+        // ...but an internal, live-only subscriber must not, or it could mistake a
+        // stale notification for the one it's actually waiting on.
+        let mut live = pool.subscribe_live();
+        assert!(matches!(
+            live.try_recv(),
+            Err(broadcast::error::TryRecvError::Empty)
+        ));
+    }
+
+    #[tokio::test]
+    async fn subscribe_with_opts_auto_closes_immediately_with_no_relays() {
+        let pool = RelayPool::new();
+
+        // No relay was ever added, so no `EOSE` can ever arrive for this subscription.
+        // Auto-close must notice there was nothing to subscribe on and tear down right
+        // away instead of waiting forever.
+        let result = tokio::time::timeout(
+            Duration::from_millis(200),
+            pool.subscribe_with_opts(Vec::new(), Some(SubscribeAutoCloseOptions::default())),
+        )
+        .await;
+
+        assert!(
+            result.is_ok(),
+            "subscribe_with_opts hung waiting for EOSE from zero relays"
+        );
+        assert!(result.unwrap().is_ok());
+    }
+
+    #[tokio::test]
+    async fn subscribe_only_sends_to_read_relays() {
+        let pool = RelayPool::new();
+        let (read_relay, mut read_rx) =
+            Relay::new_for_test("wss://read.test", RelayServiceFlags::READ);
+        let (write_relay, mut write_rx) =
+            Relay::new_for_test("wss://write.test", RelayServiceFlags::WRITE);
+
+        pool.relays
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(read_relay.url(), read_relay);
+        pool.relays
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(write_relay.url(), write_relay);
+
+        pool.subscribe(Vec::new()).await.unwrap();
+
+        assert!(
+            read_rx.try_recv().is_ok(),
+            "the READ relay should have received the REQ"
+        );
+        assert!(
+            write_rx.try_recv().is_err(),
+            "the WRITE-only relay should not have received the REQ"
+        );
+    }
+
+    #[tokio::test]
+    async fn send_event_only_sends_to_write_relays() {
+        use nostr_sdk_base::Keys;
+
+        let pool = RelayPool::new();
+        let (read_relay, mut read_rx) =
+            Relay::new_for_test("wss://read.test", RelayServiceFlags::READ);
+        let (write_relay, mut write_rx) =
+            Relay::new_for_test("wss://write.test", RelayServiceFlags::WRITE);
+        let write_url = write_relay.url();
+
+        pool.relays
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(read_relay.url(), read_relay);
+        pool.relays
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(write_relay.url(), write_relay);
+
+        let keys = Keys::generate_from_os_random();
+        let event = Event::set_contact_list(&keys, Vec::new(), None).expect("valid event");
+
+        let outcomes = pool.send_event(event).await.unwrap();
+
+        assert_eq!(outcomes.len(), 1, "only the WRITE relay should be sent to");
+        assert_eq!(outcomes[0].0, write_url);
+        assert!(outcomes[0].1.is_ok());
+        assert!(
+            write_rx.try_recv().is_ok(),
+            "the WRITE relay should have received the EVENT"
+        );
+        assert!(
+            read_rx.try_recv().is_err(),
+            "the READ-only relay should not have received the EVENT"
+        );
+    }
+
+    #[tokio::test]
+    async fn connection_events_forwards_relay_connection_events() {
+        let pool = RelayPool::new();
+        let mut events = pool.connection_events();
+
+        pool.connection_sender
+            .send(RelayConnectionEvent::Connected {
+                url: "wss://relay.test".to_string(),
+            })
+            .await
+            .unwrap();
+
+        let event = tokio::time::timeout(Duration::from_millis(200), events.recv())
+            .await
+            .expect("connection_events should deliver the forwarded event")
+            .unwrap();
+
+        assert!(matches!(
+            event,
+            RelayConnectionEvent::Connected { url } if url == "wss://relay.test"
+        ));
+    }
+
+    #[tokio::test]
+    async fn fan_out_isolates_a_single_relay_failure() {
+        let pool = RelayPool::new();
+        let (good_relay, mut good_rx) =
+            Relay::new_for_test("wss://good.test", RelayServiceFlags::READ);
+        let (bad_relay, bad_rx) = Relay::new_for_test("wss://bad.test", RelayServiceFlags::READ);
+        // Drop the receiving half so sending to this relay fails immediately, the way a
+        // relay connection that has gone away would.
+        drop(bad_rx);
+
+        pool.relays
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(good_relay.url(), good_relay);
+        pool.relays
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(bad_relay.url(), bad_relay);
+
+        // subscribe_with_id shares the same bounded, concurrent FuturesUnordered
+        // fan-out as send_event: a relay with a closed queue must fail fast and be
+        // reported, without blocking delivery to the other relay.
+        let result = tokio::time::timeout(Duration::from_millis(200), pool.subscribe(Vec::new())).await;
+
+        assert!(
+            result.is_ok(),
+            "a closed relay queue should fail fast, not hang the whole fan-out"
+        );
+        assert!(
+            result.unwrap().is_err(),
+            "the closed relay should be surfaced as an error"
+        );
+        assert!(
+            good_rx.try_recv().is_ok(),
+            "the healthy relay should still have received its REQ"
+        );
+    }
+}